@@ -0,0 +1,334 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
+
+use regex::Regex;
+
+///
+/// How much a proxy gives away about the client behind it
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum AnonymityLevel {
+    /// The target sees the real address of the client
+    Transparent,
+    /// The target can tell a proxy is used, but not who uses it
+    Anonymous,
+    /// The target can't tell a proxy is used
+    Elite,
+}
+
+///
+/// Check which address a target sees and what the proxy tells it about the client
+///
+/// The check requests an echo endpoint through every working proxy. The endpoint has to
+/// answer with the address it sees and the headers it received, either as JSON like
+/// `{"origin": "203.0.113.7", "headers": {"Via": "1.1 proxy"}}` or as lines like
+/// `X-Forwarded-For: 192.0.2.1` or `REMOTE_ADDR = 203.0.113.7`. Endpoints which answer
+/// with nothing but the address work too, but can't reveal any headers.
+///
+/// # Examples
+///
+/// ```rust
+/// use proxytester::{AnonymityCheck, ProxyTesterOptions};
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_anonymity_check(AnonymityCheck::new("https://httpbin.org/get".to_owned()))
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnonymityCheck {
+    echo_url: String,
+    real_ip: Option<IpAddr>,
+}
+
+/// Headers which carry the address of the client
+const FORWARDING_HEADERS: [&str; 11] = [
+    "x-forwarded-for",
+    "forwarded",
+    "forwarded-for",
+    "x-forwarded",
+    "x-real-ip",
+    "client-ip",
+    "x-client-ip",
+    "x-originating-ip",
+    "x-cluster-client-ip",
+    "true-client-ip",
+    "cf-connecting-ip",
+];
+
+/// Headers which only reveal that a proxy is in between
+const PROXY_HEADERS: [&str; 4] = ["via", "proxy-connection", "x-proxy-id", "x-bluecoat-via"];
+
+/// Fields of an echo response which hold the address the endpoint sees
+const EXIT_IP_FIELDS: [&str; 3] = ["origin", "ip", "remote-addr"];
+
+///
+/// What an echo endpoint saw of a request
+///
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Echo {
+    pub(crate) exit_ip: Option<IpAddr>,
+    /// The fields of the response with normalized names, see [normalize]
+    fields: Vec<(String, String)>,
+}
+
+impl AnonymityCheck {
+    ///
+    /// Create a check against the echo endpoint
+    ///
+    /// The real address of the client is looked up through the endpoint without
+    /// a proxy when a run starts, unless it's set.
+    ///
+    pub fn new(echo_url: String) -> AnonymityCheck {
+        AnonymityCheck {
+            echo_url,
+            real_ip: None,
+        }
+    }
+
+    ///
+    /// Set the real address of the client instead of looking it up
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_real_ip(mut self, real_ip: IpAddr) -> Self {
+        self.real_ip = Option::from(real_ip);
+        self
+    }
+
+    ///
+    /// Get the URL of the echo endpoint
+    ///
+    pub fn echo_url(&self) -> &str {
+        &self.echo_url
+    }
+
+    ///
+    /// Get the real address of the client, if it was set or looked up
+    ///
+    pub fn real_ip(&self) -> Option<IpAddr> {
+        self.real_ip
+    }
+}
+
+impl Echo {
+    ///
+    /// Parse the response of an echo endpoint
+    ///
+    pub(crate) fn parse(body: &str) -> Echo {
+        static JSON_FIELD: OnceLock<Regex> = OnceLock::new();
+        static TEXT_FIELD: OnceLock<Regex> = OnceLock::new();
+        let json_field = JSON_FIELD
+            .get_or_init(|| Regex::new(r#""([^"]+)"\s*:\s*"([^"]*)""#).expect("valid regex"));
+        let text_field = TEXT_FIELD.get_or_init(|| {
+            Regex::new(r"(?m)^\s*([A-Za-z][A-Za-z0-9_-]*)\s*[:=]\s*(.*?)\s*$").expect("valid regex")
+        });
+
+        let fields = json_field
+            .captures_iter(body)
+            .chain(text_field.captures_iter(body))
+            .map(|captures| (normalize(&captures[1]), captures[2].to_owned()))
+            .collect::<Vec<_>>();
+
+        // The address is the last hop when the endpoint lists the whole chain
+        let exit_ip = fields
+            .iter()
+            .filter(|(name, _)| EXIT_IP_FIELDS.contains(&name.as_str()))
+            .find_map(|(_, value)| addresses(value).pop())
+            .or_else(|| body.trim().parse().ok());
+
+        Echo { exit_ip, fields }
+    }
+
+    ///
+    /// Classify the anonymity of the proxy the echo went through
+    ///
+    /// Without the real address, forwarding headers carrying any address but the exit
+    /// address count as a leak. The exit address itself is ignored in those headers,
+    /// since endpoints behind a load balancer often receive it that way.
+    ///
+    pub(crate) fn classify(&self, real_ip: Option<IpAddr>) -> AnonymityLevel {
+        let forwarded = self
+            .fields
+            .iter()
+            .filter(|(name, _)| FORWARDING_HEADERS.contains(&name.as_str()))
+            .filter(|(_, value)| {
+                let addresses = addresses(value);
+                addresses.is_empty() || addresses.iter().any(|ip| Some(*ip) != self.exit_ip)
+            })
+            .collect::<Vec<_>>();
+
+        let leaked = match real_ip {
+            Some(real_ip) => {
+                self.exit_ip == Some(real_ip)
+                    || self
+                        .fields
+                        .iter()
+                        .any(|(_, value)| addresses(value).contains(&real_ip))
+            }
+            None => forwarded
+                .iter()
+                .any(|(_, value)| !addresses(value).is_empty()),
+        };
+
+        if leaked {
+            AnonymityLevel::Transparent
+        } else if !forwarded.is_empty()
+            || self
+                .fields
+                .iter()
+                .any(|(name, _)| PROXY_HEADERS.contains(&name.as_str()))
+        {
+            AnonymityLevel::Anonymous
+        } else {
+            AnonymityLevel::Elite
+        }
+    }
+}
+
+///
+/// Normalize a field name, so `X-Forwarded-For` and `HTTP_X_FORWARDED_FOR` are the same
+///
+fn normalize(name: &str) -> String {
+    let name = name.to_ascii_lowercase().replace('_', "-");
+    match name.strip_prefix("http-") {
+        Some(name) => name.to_owned(),
+        None => name,
+    }
+}
+
+///
+/// Find the addresses in a header value
+///
+/// Understands lists like `192.0.2.1, 198.51.100.2` as well as
+/// `for="[2001:db8::1]:4711";proto=http` from the Forwarded header.
+///
+fn addresses(value: &str) -> Vec<IpAddr> {
+    value
+        .split([',', ';', ' '])
+        .filter_map(|part| {
+            let part = part.rsplit_once('=').map_or(part, |(_, value)| value);
+            let part = part.trim().trim_matches('"');
+            part.parse::<IpAddr>()
+                .or_else(|_| part.parse::<SocketAddr>().map(|addr| addr.ip()))
+                .or_else(|_| part.trim_start_matches('[').trim_end_matches(']').parse())
+                .ok()
+        })
+        .collect()
+}
+
+impl AnonymityLevel {
+    ///
+    /// Get the stable name of the level
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnonymityLevel::Transparent => "transparent",
+            AnonymityLevel::Anonymous => "anonymous",
+            AnonymityLevel::Elite => "elite",
+        }
+    }
+}
+
+impl Display for AnonymityLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REAL_IP: &str = "192.0.2.1";
+
+    fn real_ip() -> Option<IpAddr> {
+        Some(REAL_IP.parse().unwrap())
+    }
+
+    #[test]
+    fn parse_json_echo() {
+        let echo = Echo::parse(
+            r#"{
+              "args": {},
+              "headers": {
+                "Host": "httpbin.org",
+                "Via": "1.1 squid"
+              },
+              "origin": "192.0.2.1, 203.0.113.7"
+            }"#,
+        );
+
+        assert_eq!(echo.exit_ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Transparent);
+    }
+
+    #[test]
+    fn parse_compact_json_echo() {
+        let echo = Echo::parse(r#"{"ip":"2001:db8::7"}"#);
+
+        assert_eq!(echo.exit_ip, Some("2001:db8::7".parse().unwrap()));
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Elite);
+    }
+
+    #[test]
+    fn parse_text_echo() {
+        let echo = Echo::parse("REMOTE_ADDR = 203.0.113.7\nHTTP_VIA = 1.1 proxy\n");
+
+        assert_eq!(echo.exit_ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Anonymous);
+    }
+
+    #[test]
+    fn parse_bare_address_echo() {
+        let echo = Echo::parse("203.0.113.7\n");
+
+        assert_eq!(echo.exit_ip, Some("203.0.113.7".parse().unwrap()));
+        assert_eq!(echo.classify(None), AnonymityLevel::Elite);
+    }
+
+    #[test]
+    fn forwarded_header_leaks_real_address() {
+        let echo = Echo::parse(
+            "ip: 203.0.113.7\nForwarded: for=\"[2001:db8::1]:4711\";proto=http, for=192.0.2.1\n",
+        );
+
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Transparent);
+    }
+
+    #[test]
+    fn forwarding_header_without_real_address_is_anonymous() {
+        let echo = Echo::parse("ip: 203.0.113.7\nX-Forwarded-For: unknown\n");
+
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Anonymous);
+    }
+
+    #[test]
+    fn exit_address_in_forwarding_header_is_ignored() {
+        // Added by the load balancer in front of the echo endpoint
+        let echo = Echo::parse("ip: 203.0.113.7\nX-Forwarded-For: 203.0.113.7\n");
+
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Elite);
+        assert_eq!(echo.classify(None), AnonymityLevel::Elite);
+    }
+
+    #[test]
+    fn unknown_real_address_assumes_leak() {
+        let echo = Echo::parse("ip: 203.0.113.7\nX-Real-IP: 198.51.100.2\n");
+
+        assert_eq!(echo.classify(None), AnonymityLevel::Transparent);
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Anonymous);
+    }
+
+    #[test]
+    fn unchanged_address_is_transparent() {
+        let echo = Echo::parse(&format!("ip: {}\n", REAL_IP));
+
+        assert_eq!(echo.classify(real_ip()), AnonymityLevel::Transparent);
+    }
+}
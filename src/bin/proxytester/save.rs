@@ -0,0 +1,152 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use proxytester::{ProxyFormat, ProxyTest};
+
+use crate::output::{Output, OutputFormat};
+
+///
+/// Writes the working proxies and the results to files as they come in
+///
+/// Every result is written before the next one is awaited, so an aborted run
+/// still leaves the results up to that point.
+///
+#[derive(Default)]
+pub struct Saver {
+    working: Option<(File, ProxyFormat)>,
+    results: Option<Output<File>>,
+}
+
+impl Saver {
+    ///
+    /// Create the file for the working proxies, written in the given format
+    ///
+    pub fn save_working(mut self, path: &Path, format: ProxyFormat) -> io::Result<Saver> {
+        self.working = Option::from((File::create(path)?, format));
+        Ok(self)
+    }
+
+    ///
+    /// Create the file for the results of the targets, written in the given format
+    ///
+    /// The working proxies are always saved with their credentials, the results only
+    /// if they are revealed.
+    ///
+    pub fn save_results(
+        mut self,
+        path: &Path,
+        format: OutputFormat,
+        urls: Vec<String>,
+        reveal_credentials: bool,
+    ) -> io::Result<Saver> {
+        let output = Output::new(format, urls, File::create(path)?)?;
+        self.results = Option::from(output.set_reveal_credentials(reveal_credentials));
+        Ok(self)
+    }
+
+    ///
+    /// Write the result of a proxy to the files
+    ///
+    pub fn save(&mut self, test: &ProxyTest) -> io::Result<()> {
+        if let Some((file, format)) = &mut self.working {
+            if test.result.is_ok() {
+                // Proxies the format can't hold are written as URLs, which auto detection reads
+                let line = test
+                    .proxy
+                    .to_string_in(*format)
+                    .unwrap_or_else(|| test.proxy.to_connection_string());
+                file.write_all(format!("{}\n", line).as_bytes())?;
+            }
+        }
+        if let Some(results) = &mut self.results {
+            results.write(test)?;
+        }
+        Ok(())
+    }
+}
+
+///
+/// Choose the format of a results file from its extension, CSV for `.csv` and NDJSON otherwise
+///
+pub fn results_format(path: &Path) -> OutputFormat {
+    match path.extension() {
+        Some(extension) if extension.eq_ignore_ascii_case("csv") => OutputFormat::Csv,
+        _ => OutputFormat::Ndjson,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use proxytester::{Proxy, ProxyTestError, ProxyTestSuccess};
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn working_proxies_and_results_are_saved() {
+        let dir = TempDir::new("proxytester").unwrap();
+        let working = dir.path().join("working.txt");
+        let results = dir.path().join("results.csv");
+
+        let mut saver = Saver::default()
+            .save_working(&working, ProxyFormat::HostPortUsernamePassword)
+            .unwrap()
+            .save_results(
+                &results,
+                results_format(&results),
+                vec!["https://example.com".to_owned()],
+                false,
+            )
+            .unwrap();
+
+        for (line, result) in [
+            (
+                "socks5://host:1080:user:pass",
+                Ok(ProxyTestSuccess {
+                    duration: Duration::from_millis(100),
+                    ..Default::default()
+                }),
+            ),
+            (
+                "other:1234:user:pass",
+                Err(ProxyTestError::TargetHttpError { status: 403 }),
+            ),
+            (
+                "socks5://user:pass@[2001:db8::1]:1080",
+                Ok(ProxyTestSuccess::default()),
+            ),
+        ] {
+            saver
+                .save(&ProxyTest {
+                    proxy: Proxy::from_str(ProxyFormat::Auto, line).unwrap(),
+                    result,
+                    attempts: 1,
+                    samples: Default::default(),
+                    targets: Vec::new(),
+                })
+                .unwrap();
+        }
+
+        // Everything is on disk while the saver is still open
+        assert_eq!(
+            std::fs::read_to_string(&working).unwrap(),
+            "socks5://host:1080:user:pass\nsocks5://user:pass@[2001:db8::1]:1080\n"
+        );
+        let results = std::fs::read_to_string(&results).unwrap();
+        assert_eq!(results.lines().count(), 4);
+        assert!(results.lines().nth(1).unwrap().contains("user:***@host"));
+        assert!(results.lines().nth(2).unwrap().contains(",target_http,"));
+    }
+
+    #[test]
+    fn results_format_follows_extension() {
+        assert_eq!(results_format(Path::new("out.CSV")), OutputFormat::Csv);
+        assert_eq!(results_format(Path::new("out.json")), OutputFormat::Ndjson);
+        assert_eq!(results_format(Path::new("out")), OutputFormat::Ndjson);
+    }
+}
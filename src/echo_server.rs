@@ -0,0 +1,99 @@
+//!
+//! A tiny echo endpoint for the tests, answering with the address and headers it sees
+//!
+//! It answers requests sent to it as a proxy the same way, so one server plays both
+//! the proxy and the echo endpoint behind it.
+//!
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+pub(crate) struct EchoServer {
+    port: u16,
+}
+
+impl EchoServer {
+    ///
+    /// Start the server, adding the headers to every request as a proxy would
+    ///
+    pub(crate) fn start(added_headers: &[(&str, &str)]) -> EchoServer {
+        EchoServer::spawn(added_headers, &[])
+    }
+
+    ///
+    /// Start the server, reporting the exit addresses in turn as a rotating gateway would
+    ///
+    pub(crate) fn start_rotating(exit_ips: &[&str]) -> EchoServer {
+        EchoServer::spawn(&[], exit_ips)
+    }
+
+    fn spawn(added_headers: &[(&str, &str)], exit_ips: &[&str]) -> EchoServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind echo server");
+        let port = listener.local_addr().expect("echo server address").port();
+        let added_headers = added_headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}\n", name, value))
+            .collect::<String>();
+        let mut exit_ips = exit_ips
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .cycle();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let added_headers = added_headers.clone();
+                let exit_ip = exit_ips.next();
+                thread::spawn(move || echo(stream, &added_headers, exit_ip));
+            }
+        });
+
+        EchoServer { port }
+    }
+
+    pub(crate) fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub(crate) fn url(&self) -> String {
+        format!("http://127.0.0.1:{}/", self.port)
+    }
+}
+
+fn echo(stream: TcpStream, added_headers: &str, exit_ip: Option<String>) {
+    let Ok(peer) = stream.peer_addr() else {
+        return;
+    };
+    let exit_ip = exit_ip.unwrap_or_else(|| peer.ip().to_string());
+    let mut reader = BufReader::new(&stream);
+
+    // Skip the request line, echo every header up to the blank line
+    let mut body = format!("ip: {}\n{}", exit_ip, added_headers);
+    let mut line = String::new();
+    if reader.read_line(&mut line).unwrap_or(0) == 0 {
+        return;
+    }
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim().is_empty() {
+            break;
+        }
+        // Headers meant for the proxy end there
+        if line.to_ascii_lowercase().starts_with("proxy-") {
+            continue;
+        }
+        body.push_str(line.trim_end());
+        body.push('\n');
+    }
+
+    let _ = write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+}
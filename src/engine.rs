@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use curl::{
+    easy::{Easy2, Handler, InfoType, WriteError},
+    multi::{Easy2Handle, Multi, MultiWaker},
+};
+use tokio::sync::oneshot;
+
+use crate::{ProxyAuthScheme, ProxyTestError, TlsSession, MAX_INSPECTED_BODY_SIZE};
+
+///
+/// The engine which performs the requests of a ProxyTester
+///
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ProxyTestEngine {
+    /// Perform every request with its own curl handle on tokio's blocking thread pool
+    #[default]
+    Blocking,
+    /// Drive every request from a single curl multi handle on one dedicated thread
+    ///
+    /// No thread is tied up per in-flight request, so the worker count can go far beyond
+    /// the size of tokio's blocking thread pool.
+    Multi,
+}
+
+///
+/// How a request goes through the proxy, which decides how its tunnel can be observed
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Tunnel {
+    /// The request is sent to the proxy as is
+    None,
+    /// A CONNECT request opens the tunnel, which is up once its response has been read
+    Connect,
+    /// The SOCKS handshake opens the tunnel, which is up once anything is sent through it
+    Socks,
+}
+
+///
+/// Handler for the curl requests made by the engines
+///
+/// Besides collecting the response, it takes note of when things happen on the wire,
+/// which curl has no timers for.
+///
+#[derive(Debug)]
+pub(crate) struct Collector {
+    tunnel: Tunnel,
+    connect_response_read: bool,
+    keep_body: bool,
+
+    /// The headers of the last response, which is the final one
+    pub(crate) headers: Vec<(String, String)>,
+    /// The start of the body if it is kept, see [MAX_INSPECTED_BODY_SIZE]
+    pub(crate) body: Vec<u8>,
+    pub(crate) body_size: usize,
+
+    /// When the tunnel through the proxy was established
+    pub(crate) tunnel_established: Option<Instant>,
+    /// When the first byte of the target's response came in
+    pub(crate) first_response_byte: Option<Instant>,
+    /// The TLS connections in the order they came up, the one to the proxy first
+    pub(crate) tls_sessions: Vec<TlsSession>,
+    /// The scheme the credentials were presented to the proxy with, None without credentials
+    pub(crate) auth_scheme: Option<ProxyAuthScheme>,
+}
+
+impl Collector {
+    pub(crate) fn new(tunnel: Tunnel, keep_body: bool) -> Collector {
+        Collector {
+            tunnel,
+            connect_response_read: false,
+            keep_body,
+            headers: Vec::new(),
+            body: Vec::new(),
+            body_size: 0,
+            tunnel_established: None,
+            first_response_byte: None,
+            tls_sessions: Vec::new(),
+            auth_scheme: None,
+        }
+    }
+
+    ///
+    /// Check if the tunnel, if any, has been established
+    ///
+    fn tunnel_ready(&self) -> bool {
+        self.tunnel == Tunnel::None || self.tunnel_established.is_some()
+    }
+}
+
+impl Handler for Collector {
+    fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
+        self.body_size += data.len();
+        if self.keep_body {
+            let room = MAX_INSPECTED_BODY_SIZE.saturating_sub(self.body.len());
+            self.body.extend_from_slice(&data[..data.len().min(room)]);
+        }
+        Ok(data.len())
+    }
+
+    fn header(&mut self, data: &[u8]) -> bool {
+        let line = String::from_utf8_lossy(data);
+
+        // Every response starts over, be it after a CONNECT, a 100 Continue or a redirect
+        if line.starts_with("HTTP/") {
+            self.headers.clear();
+        } else if let Some((name, value)) = line.split_once(':') {
+            self.headers
+                .push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+        true
+    }
+
+    fn debug(&mut self, kind: InfoType, data: &[u8]) {
+        let now = Instant::now();
+
+        match kind {
+            // The blank line ends the headers of the CONNECT response
+            InfoType::HeaderIn
+                if self.tunnel == Tunnel::Connect
+                    && !self.tunnel_ready()
+                    && !self.connect_response_read =>
+            {
+                self.connect_response_read = data == b"\r\n";
+            }
+            InfoType::HeaderIn if self.tunnel_ready() => {
+                self.first_response_byte.get_or_insert(now);
+            }
+            // The first thing sent through the tunnel, either the TLS handshake
+            // or the request itself
+            InfoType::HeaderOut | InfoType::SslDataOut
+                if self.tunnel_established.is_none()
+                    && (self.tunnel == Tunnel::Socks || self.connect_response_read) =>
+            {
+                self.tunnel_established = Some(now);
+            }
+            InfoType::Text => {
+                if let Some(session) = TlsSession::parse(&String::from_utf8_lossy(data)) {
+                    self.tls_sessions.push(session);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) type Request = Easy2<Collector>;
+
+/// A finished request together with the outcome of performing it
+pub(crate) type Completed = (Request, Result<(), curl::Error>);
+
+/// How long the multi thread sleeps at most before checking on its transfers
+const MULTI_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub(crate) enum Engine {
+    Blocking,
+    Multi(MultiEngine),
+}
+
+impl Engine {
+    ///
+    /// Create the engine, starting the multi thread if needed
+    ///
+    pub(crate) fn new(kind: ProxyTestEngine) -> Engine {
+        match kind {
+            ProxyTestEngine::Blocking => Engine::Blocking,
+            ProxyTestEngine::Multi => Engine::Multi(MultiEngine::new()),
+        }
+    }
+
+    ///
+    /// Perform a prepared request
+    ///
+    /// The request is handed back so information can be read from it afterwards.
+    ///
+    pub(crate) async fn perform(&self, request: Request) -> Result<Completed, ProxyTestError> {
+        match self {
+            Engine::Blocking => Ok(tokio::task::spawn_blocking(move || {
+                let result = request.perform();
+                (request, result)
+            })
+            .await
+            .expect("join error, this should never happen")),
+            Engine::Multi(multi) => multi.perform(request).await,
+        }
+    }
+}
+
+struct Job {
+    request: Request,
+    done: oneshot::Sender<Completed>,
+}
+
+pub(crate) struct MultiEngine {
+    jobs: mpsc::Sender<Job>,
+    waker: MultiWaker,
+}
+
+impl MultiEngine {
+    ///
+    /// Start the thread which owns the multi handle
+    ///
+    /// The thread stops once the engine is dropped and all its transfers are done.
+    ///
+    fn new() -> MultiEngine {
+        let (jobs, job_receiver) = mpsc::channel();
+        let (waker_sender, waker_receiver) = mpsc::sync_channel(1);
+
+        thread::Builder::new()
+            .name("proxytester-multi".to_owned())
+            .spawn(move || {
+                // The multi handle can't leave this thread, only its waker can
+                let multi = Multi::new();
+                waker_sender
+                    .send(multi.waker())
+                    .expect("engine dropped while starting, this should never happen");
+
+                drive(multi, job_receiver);
+            })
+            .expect("failed to spawn the multi thread");
+
+        let waker = waker_receiver
+            .recv()
+            .expect("multi thread died while starting, this should never happen");
+
+        MultiEngine { jobs, waker }
+    }
+
+    async fn perform(&self, request: Request) -> Result<Completed, ProxyTestError> {
+        let (done, completed) = oneshot::channel();
+        self.jobs
+            .send(Job { request, done })
+            .map_err(|_| ProxyTestError::UnknownError)?;
+        // Wake the thread up in case it's waiting on other transfers
+        self.waker
+            .wakeup()
+            .map_err(|_| ProxyTestError::UnknownError)?;
+
+        completed.await.map_err(|_| ProxyTestError::UnknownError)
+    }
+}
+
+///
+/// Drive all transfers of the multi handle until no more jobs can arrive
+///
+fn drive(multi: Multi, jobs: mpsc::Receiver<Job>) {
+    let mut transfers: HashMap<usize, (Easy2Handle<Collector>, oneshot::Sender<Completed>)> =
+        HashMap::new();
+    let mut next_token = 0;
+
+    let mut add = |multi: &Multi, transfers: &mut HashMap<_, _>, job: Job| {
+        // If the request can't be added, dropping `done` reports the failure
+        let Ok(mut handle) = multi.add2(job.request) else {
+            return;
+        };
+        if handle.set_token(next_token).is_err() {
+            let _ = multi.remove2(handle);
+            return;
+        }
+        transfers.insert(next_token, (handle, job.done));
+        next_token = next_token.wrapping_add(1);
+    };
+
+    loop {
+        // Block until there is work when idle, stop once every sender is gone
+        if transfers.is_empty() {
+            match jobs.recv() {
+                Ok(job) => add(&multi, &mut transfers, job),
+                Err(_) => return,
+            }
+        }
+        while let Ok(job) = jobs.try_recv() {
+            add(&multi, &mut transfers, job);
+        }
+
+        if multi.perform().is_err() {
+            // The multi handle is unusable, fail everything in flight
+            return;
+        }
+
+        let mut finished = Vec::new();
+        multi.messages(|message| {
+            if let (Ok(token), Some(result)) = (message.token(), message.result()) {
+                finished.push((token, result));
+            }
+        });
+
+        for (token, result) in finished {
+            if let Some((handle, done)) = transfers.remove(&token) {
+                if let Ok(request) = multi.remove2(handle) {
+                    let _ = done.send((request, result));
+                }
+            }
+        }
+
+        if !transfers.is_empty() {
+            let _ = multi.poll(&mut [], MULTI_POLL_TIMEOUT);
+        }
+    }
+}
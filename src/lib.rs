@@ -1,5 +1,31 @@
+mod anonymity;
+#[cfg(test)]
+mod echo_server;
+mod engine;
 mod proxy;
 mod proxytester;
+#[cfg(feature = "serde")]
+mod redact;
+mod request;
+mod retry;
+mod rotation;
+mod samples;
+mod source;
+mod target;
+mod tls;
+mod validation;
 
+pub use anonymity::{AnonymityCheck, AnonymityLevel};
+pub use engine::ProxyTestEngine;
 pub use proxy::*;
 pub use proxytester::*;
+#[cfg(feature = "serde")]
+pub use redact::Redacted;
+pub use request::*;
+pub use retry::*;
+pub use rotation::*;
+pub use samples::*;
+pub use source::*;
+pub use target::Target;
+pub use tls::*;
+pub use validation::*;
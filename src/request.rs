@@ -0,0 +1,271 @@
+use std::path::PathBuf;
+
+use curl::easy::List;
+
+use crate::{engine::Request, ProxyTesterConfigError};
+
+///
+/// How the targets are requested
+///
+/// By default the targets are requested with a bare GET. Targets which only show the
+/// behavior of interest to a certain client can be requested with a different method,
+/// headers, a body or by following redirects. The template only applies to the targets,
+/// the echo endpoints of the checks are always requested with a bare GET.
+///
+/// # Examples
+///
+/// ```rust
+/// use proxytester::{ProxyTesterOptions, RequestTemplate};
+///
+/// let template = RequestTemplate::new()
+///     .set_method("POST")
+///     .add_header("Content-Type", "application/json")
+///     .add_header("Cookie", "session=1234")
+///     .set_body(r#"{"query": "status"}"#)
+///     .set_user_agent("Mozilla/5.0")
+///     .set_follow_redirects(true)
+///     .set_max_redirects(5);
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_request_template(template)
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestTemplate {
+    method: String,
+    headers: Vec<(String, String)>,
+    body: Option<RequestBody>,
+    user_agent: Option<String>,
+    follow_redirects: bool,
+    max_redirects: Option<u32>,
+}
+
+///
+/// Where the body of a request comes from
+///
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RequestBody {
+    Bytes(Vec<u8>),
+    /// A file which is read once when the tester is built
+    File(PathBuf),
+}
+
+impl RequestTemplate {
+    ///
+    /// Create a template for a bare GET
+    ///
+    pub fn new() -> RequestTemplate {
+        RequestTemplate {
+            method: "GET".to_owned(),
+            headers: Vec::new(),
+            body: None,
+            user_agent: None,
+            follow_redirects: false,
+            max_redirects: None,
+        }
+    }
+
+    ///
+    /// Set the method of the request, like POST or HEAD
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_method<S: Into<String>>(mut self, method: S) -> Self {
+        self.method = method.into();
+        self
+    }
+
+    ///
+    /// Add a header to the request, replacing the one curl would send by default
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_header<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    ///
+    /// Set the body of the request
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = Option::from(RequestBody::Bytes(body.into()));
+        self
+    }
+
+    ///
+    /// Set a file to read the body of the request from
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_body_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.body = Option::from(RequestBody::File(path.into()));
+        self
+    }
+
+    ///
+    /// Set the User-Agent header of the request
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+        self.user_agent = Option::from(user_agent.into());
+        self
+    }
+
+    ///
+    /// Set whether redirects are followed, the final response is validated then
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_follow_redirects(mut self, follow_redirects: bool) -> Self {
+        self.follow_redirects = follow_redirects;
+        self
+    }
+
+    ///
+    /// Set how many redirects are followed at most, unlimited by default
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_max_redirects(mut self, max_redirects: u32) -> Self {
+        self.max_redirects = Option::from(max_redirects);
+        self
+    }
+
+    ///
+    /// Get the method of the request
+    ///
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    ///
+    /// Get the headers added to the request
+    ///
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    ///
+    /// Get the body of the request, if it has one
+    ///
+    pub fn body(&self) -> Option<&RequestBody> {
+        self.body.as_ref()
+    }
+
+    ///
+    /// Get the User-Agent header of the request, if it's set
+    ///
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    ///
+    /// Check if redirects are followed
+    ///
+    pub fn follow_redirects(&self) -> bool {
+        self.follow_redirects
+    }
+
+    ///
+    /// Get how many redirects are followed at most, None if unlimited
+    ///
+    pub fn max_redirects(&self) -> Option<u32> {
+        self.max_redirects
+    }
+
+    ///
+    /// Check the method, headers and user agent and read the body file, if there is one
+    ///
+    pub(crate) fn load(mut self) -> Result<RequestTemplate, ProxyTesterConfigError> {
+        let is_token = |value: &str| {
+            !value.is_empty()
+                && value
+                    .bytes()
+                    .all(|byte| byte.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&byte))
+        };
+
+        if !is_token(&self.method) {
+            return Err(ProxyTesterConfigError::InvalidMethodError(self.method));
+        }
+        if let Some((name, _)) = self.headers.iter().find(|(name, _)| !is_token(name)) {
+            return Err(ProxyTesterConfigError::InvalidHeaderError(name.clone()));
+        }
+        // A line break would end the header and let the value inject others
+        let has_line_break = |value: &str| value.contains(['\r', '\n']);
+        if let Some((name, _)) = self.headers.iter().find(|(_, value)| has_line_break(value)) {
+            return Err(ProxyTesterConfigError::InvalidHeaderValueError(
+                name.clone(),
+            ));
+        }
+        if self.user_agent.as_deref().is_some_and(has_line_break) {
+            return Err(ProxyTesterConfigError::InvalidHeaderValueError(
+                "User-Agent".to_owned(),
+            ));
+        }
+
+        if let Some(RequestBody::File(path)) = self.body {
+            let body = std::fs::read(&path)
+                .map_err(|source| ProxyTesterConfigError::BodyFileError { path, source })?;
+            self.body = Option::from(RequestBody::Bytes(body));
+        }
+        Ok(self)
+    }
+
+    ///
+    /// Set up the request as the template says
+    ///
+    pub(crate) fn apply(&self, request: &mut Request) -> Result<(), curl::Error> {
+        // Body files were read when the tester was built
+        let body = match &self.body {
+            Some(RequestBody::Bytes(body)) => Some(body.as_slice()),
+            Some(RequestBody::File(_)) | None => None,
+        };
+
+        match (body, self.method.as_str()) {
+            (None, "GET") => {}
+            (None, "HEAD") => request.nobody(true)?,
+            (None, method) if method != "POST" => request.custom_request(method)?,
+            (body, method) => {
+                request.post(true)?;
+                request.post_fields_copy(body.unwrap_or_default())?;
+                if method != "POST" {
+                    request.custom_request(method)?;
+                }
+            }
+        }
+
+        if !self.headers.is_empty() {
+            let mut headers = List::new();
+            for (name, value) in &self.headers {
+                headers.append(&format!("{}: {}", name, value))?;
+            }
+            request.http_headers(headers)?;
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            request.useragent(user_agent)?;
+        }
+
+        if self.follow_redirects {
+            request.follow_location(true)?;
+            if let Some(max_redirects) = self.max_redirects {
+                request.max_redirections(max_redirects)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for RequestTemplate {
+    fn default() -> Self {
+        RequestTemplate::new()
+    }
+}
@@ -0,0 +1,231 @@
+use std::{collections::HashSet, time::Duration};
+
+use crate::ProxyTestErrorKind;
+
+///
+/// When and how often a failed probe is tried again
+///
+/// By default a probe is tried once. Only failures of a retryable kind are retried,
+/// which are timeouts unless chosen otherwise, since a proxy asking for authentication
+/// won't change its mind.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use proxytester::{Backoff, ProxyTestErrorKind, ProxyTesterOptions, RetryPolicy};
+///
+/// let policy = RetryPolicy::new(3)
+///     .set_backoff(Backoff::Exponential {
+///         initial: Duration::from_millis(200),
+///         max: Duration::from_secs(2),
+///     })
+///     .set_jitter(0.5)
+///     .add_retryable(ProxyTestErrorKind::ConnectionRefused);
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_retry_policy(policy)
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    backoff: Backoff,
+    jitter: f64,
+    retryable: HashSet<ProxyTestErrorKind>,
+}
+
+///
+/// How long to wait before trying again
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Backoff {
+    /// Wait the same time before every retry
+    Fixed(Duration),
+    /// Wait `initial` before the first retry and double it for every following one,
+    /// up to `max`
+    Exponential { initial: Duration, max: Duration },
+}
+
+/// The failures which are retried unless chosen otherwise
+const DEFAULT_RETRYABLE: [ProxyTestErrorKind; 2] = [
+    ProxyTestErrorKind::ConnectTimeout,
+    ProxyTestErrorKind::TotalTimeout,
+];
+
+impl RetryPolicy {
+    ///
+    /// Create a policy trying every probe up to `max_attempts` times
+    ///
+    /// Retries happen right away, see [RetryPolicy::set_backoff].
+    ///
+    pub fn new(max_attempts: usize) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff: Backoff::Fixed(Duration::ZERO),
+            jitter: 0.0,
+            retryable: HashSet::from(DEFAULT_RETRYABLE),
+        }
+    }
+
+    ///
+    /// Set how long to wait before trying again
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    ///
+    /// Set how much of the backoff is randomized, between 0 and 1
+    ///
+    /// A jitter of 0.5 waits between half and all of the backoff, so proxies which
+    /// failed together don't retry together.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    ///
+    /// Set the kinds of failures which are retried, replacing the default
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_retryable<I: IntoIterator<Item = ProxyTestErrorKind>>(mut self, kinds: I) -> Self {
+        self.retryable = kinds.into_iter().collect();
+        self
+    }
+
+    ///
+    /// Add a kind of failure which is retried
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_retryable(mut self, kind: ProxyTestErrorKind) -> Self {
+        self.retryable.insert(kind);
+        self
+    }
+
+    ///
+    /// Get the most times a probe is tried
+    ///
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    ///
+    /// Get how long to wait before trying again
+    ///
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    ///
+    /// Get how much of the backoff is randomized
+    ///
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    ///
+    /// Check if a kind of failure is retried
+    ///
+    pub fn is_retryable(&self, kind: ProxyTestErrorKind) -> bool {
+        self.retryable.contains(&kind)
+    }
+
+    ///
+    /// Get the backoff before the retry following the attempt, counted from 1, without jitter
+    ///
+    pub(crate) fn base_delay(&self, attempt: usize) -> Duration {
+        match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 1u32
+                    .checked_shl(attempt.saturating_sub(1).min(31) as u32)
+                    .unwrap_or(u32::MAX);
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+
+    ///
+    /// Get the backoff before the retry following the attempt, with jitter
+    ///
+    pub(crate) fn delay(&self, attempt: usize) -> Duration {
+        let delay = self.base_delay(attempt);
+        delay.mul_f64(1.0 - self.jitter * fastrand::f64())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_retries_timeouts_only() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.max_attempts(), 1);
+        assert!(policy.is_retryable(ProxyTestErrorKind::ConnectTimeout));
+        assert!(policy.is_retryable(ProxyTestErrorKind::TotalTimeout));
+        assert!(!policy.is_retryable(ProxyTestErrorKind::ProxyAuthRequired));
+    }
+
+    #[test]
+    fn retryable_kinds_can_be_replaced() {
+        let policy = RetryPolicy::new(2).set_retryable([ProxyTestErrorKind::ConnectionRefused]);
+
+        assert!(policy.is_retryable(ProxyTestErrorKind::ConnectionRefused));
+        assert!(!policy.is_retryable(ProxyTestErrorKind::TotalTimeout));
+    }
+
+    #[test]
+    fn fixed_backoff() {
+        let policy = RetryPolicy::new(3).set_backoff(Backoff::Fixed(Duration::from_millis(100)));
+
+        assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.base_delay(2), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn exponential_backoff_is_capped() {
+        let policy = RetryPolicy::new(10).set_backoff(Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+        });
+
+        assert_eq!(policy.base_delay(1), Duration::from_millis(100));
+        assert_eq!(policy.base_delay(2), Duration::from_millis(200));
+        assert_eq!(policy.base_delay(3), Duration::from_millis(400));
+        assert_eq!(policy.base_delay(4), Duration::from_millis(500));
+        assert_eq!(policy.base_delay(100), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_shortens_the_backoff() {
+        let policy = RetryPolicy::new(2)
+            .set_backoff(Backoff::Fixed(Duration::from_millis(100)))
+            .set_jitter(0.5);
+
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{collections::HashSet, fmt::Display, net::IpAddr, time::Duration};
+
+use tokio::time::Instant;
+
+///
+/// Check how a proxy rotates its exit address over several requests
+///
+/// Backconnect gateways hand out a different exit address per request or per session.
+/// The check sends the requests one after the other through every working proxy to an
+/// echo endpoint, which has to answer like the one of an
+/// [AnonymityCheck](struct.AnonymityCheck.html).
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use proxytester::{ProxyTesterOptions, RotationCheck};
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_rotation_check(
+///         RotationCheck::new("https://httpbin.org/ip".to_owned(), 10)
+///             .set_delay(Duration::from_secs(1)),
+///     )
+///     .build();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RotationCheck {
+    echo_url: String,
+    requests: usize,
+    delay: Duration,
+}
+
+///
+/// How a proxy rotated its exit address
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum RotationBehavior {
+    /// Fewer than two requests told their exit address
+    Unknown,
+    /// Every request left through the same address
+    Static,
+    /// Every request left through a different address than the one before
+    PerRequest,
+    /// The address changed, but was kept for several requests in a row
+    Sticky,
+}
+
+///
+/// What the rotation check saw of a proxy
+///
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RotationReport {
+    /// The exit address of every request in order, None where the request failed
+    /// or the echo endpoint didn't tell
+    pub exit_ips: Vec<Option<IpAddr>>,
+    /// How many different exit addresses were seen
+    pub distinct_exit_ips: usize,
+    /// How often the exit address changed between requests which told it
+    pub rotations: usize,
+    /// The average time an exit address was kept until it changed, None if it never changed
+    ///
+    /// The address in use at the end is left out, as it's unknown how long it will be kept.
+    pub rotation_interval: Option<Duration>,
+    pub behavior: RotationBehavior,
+}
+
+/// The fewest requests which can tell a rotation apart from a static address
+pub const MIN_ROTATION_REQUESTS: usize = 2;
+
+impl RotationCheck {
+    ///
+    /// Create a check sending the amount of requests to the echo endpoint
+    ///
+    pub fn new(echo_url: String, requests: usize) -> RotationCheck {
+        RotationCheck {
+            echo_url,
+            requests,
+            delay: Duration::ZERO,
+        }
+    }
+
+    ///
+    /// Set how long to wait between the requests
+    ///
+    /// Gateways which rotate on a timer need a delay to show it.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    ///
+    /// Get the URL of the echo endpoint
+    ///
+    pub fn echo_url(&self) -> &str {
+        &self.echo_url
+    }
+
+    ///
+    /// Get the amount of requests sent through every proxy
+    ///
+    pub fn requests(&self) -> usize {
+        self.requests
+    }
+
+    ///
+    /// Get the delay between the requests
+    ///
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+impl RotationReport {
+    ///
+    /// Analyze the exit addresses of the requests in the order they were sent
+    ///
+    /// Requests without an address are skipped, so a failed request between two with
+    /// the same address doesn't count as a rotation.
+    ///
+    pub(crate) fn analyze(samples: Vec<(Instant, Option<IpAddr>)>) -> RotationReport {
+        let known = samples
+            .iter()
+            .filter_map(|(at, ip)| ip.map(|ip| (*at, ip)))
+            .collect::<Vec<_>>();
+
+        let distinct_exit_ips = known.iter().map(|(_, ip)| ip).collect::<HashSet<_>>().len();
+        let rotations = known
+            .windows(2)
+            .filter(|pair| pair[0].1 != pair[1].1)
+            .count();
+
+        // Every address before the last change was kept from its first request until the
+        // change, so those spans add up to the time from the first request to the last change
+        let last_change = known
+            .windows(2)
+            .rev()
+            .find(|pair| pair[0].1 != pair[1].1)
+            .map(|pair| pair[1].0);
+        let rotation_interval = match (known.first(), last_change) {
+            (Some((first, _)), Some(last_change)) => {
+                Some(last_change.duration_since(*first) / rotations as u32)
+            }
+            _ => None,
+        };
+
+        let behavior = if known.len() < MIN_ROTATION_REQUESTS {
+            RotationBehavior::Unknown
+        } else if rotations == 0 {
+            RotationBehavior::Static
+        } else if rotations == known.len() - 1 {
+            RotationBehavior::PerRequest
+        } else {
+            RotationBehavior::Sticky
+        };
+
+        RotationReport {
+            exit_ips: samples.into_iter().map(|(_, ip)| ip).collect(),
+            distinct_exit_ips,
+            rotations,
+            rotation_interval,
+            behavior,
+        }
+    }
+}
+
+impl RotationBehavior {
+    ///
+    /// Get the stable name of the behavior
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RotationBehavior::Unknown => "unknown",
+            RotationBehavior::Static => "static",
+            RotationBehavior::PerRequest => "per_request",
+            RotationBehavior::Sticky => "sticky",
+        }
+    }
+}
+
+impl Display for RotationBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///
+    /// Create samples one second apart
+    ///
+    fn samples(ips: &[Option<&str>]) -> Vec<(Instant, Option<IpAddr>)> {
+        let start = Instant::now();
+        ips.iter()
+            .enumerate()
+            .map(|(i, ip)| {
+                (
+                    start + Duration::from_secs(i as u64),
+                    ip.map(|ip| ip.parse().unwrap()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn static_address() {
+        let report = RotationReport::analyze(samples(&[
+            Some("203.0.113.1"),
+            Some("203.0.113.1"),
+            Some("203.0.113.1"),
+        ]));
+
+        assert_eq!(report.distinct_exit_ips, 1);
+        assert_eq!(report.rotations, 0);
+        assert_eq!(report.rotation_interval, None);
+        assert_eq!(report.behavior, RotationBehavior::Static);
+    }
+
+    #[test]
+    fn rotation_per_request() {
+        let report = RotationReport::analyze(samples(&[
+            Some("203.0.113.1"),
+            Some("203.0.113.2"),
+            Some("203.0.113.1"),
+        ]));
+
+        assert_eq!(report.distinct_exit_ips, 2);
+        assert_eq!(report.rotations, 2);
+        assert_eq!(report.rotation_interval, Some(Duration::from_secs(1)));
+        assert_eq!(report.behavior, RotationBehavior::PerRequest);
+    }
+
+    #[test]
+    fn sticky_sessions() {
+        let report = RotationReport::analyze(samples(&[
+            Some("203.0.113.1"),
+            Some("203.0.113.1"),
+            Some("203.0.113.1"),
+            Some("203.0.113.2"),
+            Some("203.0.113.2"),
+        ]));
+
+        assert_eq!(report.distinct_exit_ips, 2);
+        assert_eq!(report.rotations, 1);
+        // The first address was kept for three requests, the second one is still in use
+        assert_eq!(report.rotation_interval, Some(Duration::from_secs(3)));
+        assert_eq!(report.behavior, RotationBehavior::Sticky);
+    }
+
+    #[test]
+    fn rotation_interval_averages_kept_addresses() {
+        let report = RotationReport::analyze(samples(&[
+            Some("203.0.113.1"),
+            Some("203.0.113.2"),
+            Some("203.0.113.2"),
+            Some("203.0.113.2"),
+            Some("203.0.113.3"),
+            Some("203.0.113.3"),
+        ]));
+
+        // Kept for one and three seconds
+        assert_eq!(report.rotations, 2);
+        assert_eq!(report.rotation_interval, Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn failed_requests_are_skipped() {
+        let report =
+            RotationReport::analyze(samples(&[Some("203.0.113.1"), None, Some("203.0.113.1")]));
+
+        assert_eq!(report.exit_ips.len(), 3);
+        assert_eq!(report.rotations, 0);
+        assert_eq!(report.behavior, RotationBehavior::Static);
+    }
+
+    #[test]
+    fn too_few_addresses() {
+        let report = RotationReport::analyze(samples(&[Some("203.0.113.1"), None]));
+
+        assert_eq!(report.distinct_exit_ips, 1);
+        assert_eq!(report.behavior, RotationBehavior::Unknown);
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+///
+/// Statistics over repeated measurements of a proxy
+///
+/// Latencies only cover the samples which succeeded and are zero if none did, so check
+/// `successes` before ranking on them. Percentiles use the nearest rank, so every reported
+/// latency was actually measured.
+///
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleStats {
+    /// How many samples were taken
+    pub samples: usize,
+    /// How many samples succeeded
+    pub successes: usize,
+    /// The share of samples which succeeded, between 0 and 1
+    pub success_ratio: f64,
+    pub min: Duration,
+    pub median: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// The mean difference between the latencies of consecutive successful samples
+    pub jitter: Duration,
+}
+
+impl SampleStats {
+    ///
+    /// Compute the statistics from the latencies of the successful samples in the order
+    /// they were taken
+    ///
+    pub(crate) fn from_latencies(latencies: &[Duration], samples: usize) -> SampleStats {
+        if latencies.is_empty() {
+            return SampleStats {
+                samples,
+                ..Default::default()
+            };
+        }
+
+        let jitter = if latencies.len() > 1 {
+            latencies
+                .windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]))
+                .sum::<Duration>()
+                / (latencies.len() - 1) as u32
+        } else {
+            Duration::ZERO
+        };
+
+        let mut sorted = latencies.to_vec();
+        sorted.sort();
+        let percentile = |p: usize| {
+            let rank = (p * sorted.len()).div_ceil(100);
+            sorted[rank.max(1) - 1]
+        };
+
+        SampleStats {
+            samples,
+            successes: latencies.len(),
+            success_ratio: latencies.len() as f64 / samples as f64,
+            min: sorted[0],
+            median: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: sorted[sorted.len() - 1],
+            jitter,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(latencies: &[u64]) -> Vec<Duration> {
+        latencies
+            .iter()
+            .map(|latency| Duration::from_millis(*latency))
+            .collect()
+    }
+
+    #[test]
+    fn no_successes() {
+        let stats = SampleStats::from_latencies(&[], 3);
+
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.successes, 0);
+        assert_eq!(stats.success_ratio, 0.0);
+        assert_eq!(stats.median, Duration::ZERO);
+    }
+
+    #[test]
+    fn single_sample() {
+        let stats = SampleStats::from_latencies(&millis(&[120]), 1);
+
+        assert_eq!(stats.success_ratio, 1.0);
+        assert_eq!(stats.min, Duration::from_millis(120));
+        assert_eq!(stats.median, Duration::from_millis(120));
+        assert_eq!(stats.p99, Duration::from_millis(120));
+        assert_eq!(stats.max, Duration::from_millis(120));
+        assert_eq!(stats.jitter, Duration::ZERO);
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let latencies = (1..=10).rev().map(|i| i * 10).collect::<Vec<_>>();
+        let stats = SampleStats::from_latencies(&millis(&latencies), 10);
+
+        assert_eq!(stats.min, Duration::from_millis(10));
+        assert_eq!(stats.median, Duration::from_millis(50));
+        assert_eq!(stats.p90, Duration::from_millis(90));
+        assert_eq!(stats.p99, Duration::from_millis(100));
+        assert_eq!(stats.max, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn jitter_and_success_ratio() {
+        let stats = SampleStats::from_latencies(&millis(&[100, 140, 120]), 4);
+
+        assert_eq!(stats.samples, 4);
+        assert_eq!(stats.successes, 3);
+        assert_eq!(stats.success_ratio, 0.75);
+        // |100 - 140| and |140 - 120|
+        assert_eq!(stats.jitter, Duration::from_millis(30));
+    }
+}
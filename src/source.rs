@@ -0,0 +1,139 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+    path::{Path, PathBuf},
+};
+
+use crate::{Proxy, ProxyFormat, ProxyLoadError};
+
+///
+/// A source of proxies which a ProxyTester consumes lazily
+///
+/// Every `Iterator<Item = Proxy>` which can be sent to another thread is a source,
+/// see [ProxyTester::run_source](struct.ProxyTester.html#method.run_source).
+///
+pub trait ProxySource: Send + 'static {
+    ///
+    /// Get the next proxy, or None once the source is exhausted
+    ///
+    /// This may block, it is called from a thread which is allowed to.
+    ///
+    fn next_proxy(&mut self) -> Option<Proxy>;
+}
+
+impl<I> ProxySource for I
+where
+    I: Iterator<Item = Proxy> + Send + 'static,
+{
+    fn next_proxy(&mut self) -> Option<Proxy> {
+        self.next()
+    }
+}
+
+///
+/// Parse proxies line by line from a reader
+///
+/// Blank lines are skipped and surrounding whitespace is trimmed. Lines which can't be parsed
+/// yield a [ProxyLoadError::ParseError], after an IO error the reader stops.
+///
+/// # Examples
+///
+/// Stream proxies into a run, skipping bad lines
+/// ```rust
+/// use proxytester::{ProxyFormat, ProxyReader, ProxyTesterOptions};
+///
+/// # tokio_test();
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn tokio_test() {
+/// let proxy_tester = ProxyTesterOptions::default().build();
+///
+/// let reader = ProxyReader::new("127.0.0.1:1\nbad\n".as_bytes(), ProxyFormat::Auto);
+/// let mut receiver = proxy_tester.run_source(reader.filter_map(Result::ok)).await;
+///
+/// assert!(receiver.recv().await.is_some());
+/// assert!(receiver.recv().await.is_none());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ProxyReader<R> {
+    lines: Lines<R>,
+    format: ProxyFormat,
+    path: Option<PathBuf>,
+    line_number: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ProxyReader<R> {
+    ///
+    /// Create a reader parsing every line in the given format
+    ///
+    pub fn new(reader: R, format: ProxyFormat) -> ProxyReader<R> {
+        ProxyReader {
+            lines: reader.lines(),
+            format,
+            path: None,
+            line_number: 0,
+            done: false,
+        }
+    }
+}
+
+impl ProxyReader<BufReader<File>> {
+    ///
+    /// Open a file and parse every line in the given format
+    ///
+    /// Errors carry the path of the file.
+    ///
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        format: ProxyFormat,
+    ) -> Result<ProxyReader<BufReader<File>>, ProxyLoadError> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).map_err(|source| ProxyLoadError::IoError {
+            path: Some(path.clone()),
+            source,
+        })?;
+
+        let mut reader = ProxyReader::new(BufReader::new(file), format);
+        reader.path = Some(path);
+        Ok(reader)
+    }
+}
+
+impl<R: BufRead> Iterator for ProxyReader<R> {
+    type Item = Result<Proxy, ProxyLoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(source) => {
+                    self.done = true;
+                    return Some(Err(ProxyLoadError::IoError {
+                        path: self.path.clone(),
+                        source,
+                    }));
+                }
+            };
+            self.line_number += 1;
+
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            return Some(Proxy::from_str(self.format, line).map_err(|source| {
+                ProxyLoadError::ParseError {
+                    path: self.path.clone(),
+                    line_number: self.line_number,
+                    line: line.to_owned(),
+                    source,
+                }
+            }));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{validation::Validator, ResponseValidation};
+
+///
+/// An additional URL to test the proxies against
+///
+/// A proxy which reaches one site can still be blocked on another. Every target is
+/// requested through every proxy, each with its own validation rules or, without any,
+/// the rules set on [ProxyTesterOptions](struct.ProxyTesterOptions.html).
+///
+/// # Examples
+///
+/// ```rust
+/// use proxytester::{ProxyTesterOptions, ResponseValidation, Target};
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_url("https://example.com".to_owned())
+///     .add_target(
+///         Target::new("https://example.org".to_owned())
+///             .set_validation(ResponseValidation::new().add_required_body_text("Example Domain")),
+///     )
+///     .build();
+///
+/// assert_eq!(proxy_tester.target_urls().count(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Target {
+    pub(crate) url: String,
+    pub(crate) validation: Option<ResponseValidation>,
+}
+
+///
+/// A target with its validation rules ready to check responses
+///
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledTarget {
+    pub(crate) url: String,
+    pub(crate) validator: Validator,
+}
+
+impl Target {
+    ///
+    /// Create a target checked with the validation rules of the tester
+    ///
+    pub fn new(url: String) -> Target {
+        Target {
+            url,
+            validation: None,
+        }
+    }
+
+    ///
+    /// Set the validation rules of this target, replacing the ones of the tester
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_validation(mut self, validation: ResponseValidation) -> Self {
+        self.validation = Option::from(validation);
+        self
+    }
+
+    ///
+    /// Get the URL of the target
+    ///
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    ///
+    /// Get the validation rules of this target, None if the ones of the tester are used
+    ///
+    pub fn validation(&self) -> Option<&ResponseValidation> {
+        self.validation.as_ref()
+    }
+}
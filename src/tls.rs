@@ -0,0 +1,404 @@
+use std::{fmt::Display, path::PathBuf};
+
+use curl::easy::SslVersion;
+
+use crate::{engine::Request, ProxyTesterConfigError};
+
+///
+/// How TLS connections to the targets and to HTTPS proxies are set up
+///
+/// By default both sides are verified against the system's CA bundle. Targets with
+/// self-signed certificates and proxies with a CA of their own can be trusted through
+/// separate bundles, or verification can be disabled for either side.
+///
+/// # Examples
+///
+/// ```rust
+/// use proxytester::{ProxyTesterOptions, TlsOptions, TlsVersion};
+///
+/// let tls = TlsOptions::new()
+///     .set_proxy_ca_bundle("testdata/proxy-ca.pem")
+///     .set_verify_target(false)
+///     .set_min_version(TlsVersion::Tls12);
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_tls_options(tls)
+///     .try_build();
+///
+/// // The bundle doesn't exist, which is caught before anything is tested
+/// assert!(proxy_tester.is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TlsOptions {
+    ca_bundle: Option<PathBuf>,
+    proxy_ca_bundle: Option<PathBuf>,
+    verify_target: bool,
+    verify_proxy: bool,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
+    min_version: Option<TlsVersion>,
+    pinned_public_keys: Vec<String>,
+}
+
+///
+/// A version of the TLS protocol
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TlsVersion {
+    #[cfg_attr(feature = "serde", serde(rename = "1.0"))]
+    Tls10,
+    #[cfg_attr(feature = "serde", serde(rename = "1.1"))]
+    Tls11,
+    #[cfg_attr(feature = "serde", serde(rename = "1.2"))]
+    Tls12,
+    #[cfg_attr(feature = "serde", serde(rename = "1.3"))]
+    Tls13,
+}
+
+///
+/// What was negotiated for a TLS connection
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TlsSession {
+    /// The protocol version as the TLS library names it, like `TLSv1.3`
+    pub version: String,
+    /// The cipher suite as the TLS library names it, like `TLS_AES_256_GCM_SHA384`
+    pub cipher: String,
+}
+
+impl TlsOptions {
+    ///
+    /// Create options verifying both sides against the system's CA bundle
+    ///
+    pub fn new() -> TlsOptions {
+        TlsOptions {
+            ca_bundle: None,
+            proxy_ca_bundle: None,
+            verify_target: true,
+            verify_proxy: true,
+            client_cert: None,
+            client_key: None,
+            min_version: None,
+            pinned_public_keys: Vec::new(),
+        }
+    }
+
+    ///
+    /// Set the PEM bundle of the CAs to verify the targets with
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_ca_bundle<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ca_bundle = Option::from(path.into());
+        self
+    }
+
+    ///
+    /// Set the PEM bundle of the CAs to verify HTTPS proxies with
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_proxy_ca_bundle<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.proxy_ca_bundle = Option::from(path.into());
+        self
+    }
+
+    ///
+    /// Set whether the certificates of the targets are verified
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_verify_target(mut self, verify: bool) -> Self {
+        self.verify_target = verify;
+        self
+    }
+
+    ///
+    /// Set whether the certificates of HTTPS proxies are verified
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_verify_proxy(mut self, verify: bool) -> Self {
+        self.verify_proxy = verify;
+        self
+    }
+
+    ///
+    /// Set the PEM client certificate presented to the targets
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_client_cert<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.client_cert = Option::from(path.into());
+        self
+    }
+
+    ///
+    /// Set the PEM private key of the client certificate, if the certificate file lacks it
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_client_key<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.client_key = Option::from(path.into());
+        self
+    }
+
+    ///
+    /// Set the oldest TLS version accepted for the targets
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_min_version(mut self, version: TlsVersion) -> Self {
+        self.min_version = Option::from(version);
+        self
+    }
+
+    ///
+    /// Add a public key the targets may present, as `sha256//` followed by the
+    /// base64 encoded SHA-256 hash of the key
+    ///
+    /// Once a key is pinned, targets presenting any other key fail the handshake.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_pinned_public_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.pinned_public_keys.push(key.into());
+        self
+    }
+
+    ///
+    /// Get the bundle of the CAs to verify the targets with, if it's set
+    ///
+    pub fn ca_bundle(&self) -> Option<&PathBuf> {
+        self.ca_bundle.as_ref()
+    }
+
+    ///
+    /// Get the bundle of the CAs to verify HTTPS proxies with, if it's set
+    ///
+    pub fn proxy_ca_bundle(&self) -> Option<&PathBuf> {
+        self.proxy_ca_bundle.as_ref()
+    }
+
+    ///
+    /// Check if the certificates of the targets are verified
+    ///
+    pub fn verify_target(&self) -> bool {
+        self.verify_target
+    }
+
+    ///
+    /// Check if the certificates of HTTPS proxies are verified
+    ///
+    pub fn verify_proxy(&self) -> bool {
+        self.verify_proxy
+    }
+
+    ///
+    /// Get the oldest TLS version accepted for the targets, if it's set
+    ///
+    pub fn min_version(&self) -> Option<TlsVersion> {
+        self.min_version
+    }
+
+    ///
+    /// Get the pinned public keys
+    ///
+    pub fn pinned_public_keys(&self) -> &[String] {
+        &self.pinned_public_keys
+    }
+
+    ///
+    /// Check that the files can be read and the pinned keys are hashes
+    ///
+    pub(crate) fn check(&self) -> Result<(), ProxyTesterConfigError> {
+        for path in [
+            &self.ca_bundle,
+            &self.proxy_ca_bundle,
+            &self.client_cert,
+            &self.client_key,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            std::fs::File::open(path).map_err(|source| ProxyTesterConfigError::TlsFileError {
+                path: path.clone(),
+                source,
+            })?;
+        }
+
+        if self.client_key.is_some() && self.client_cert.is_none() {
+            return Err(ProxyTesterConfigError::ClientKeyWithoutCertError);
+        }
+
+        if let Some(key) = self
+            .pinned_public_keys
+            .iter()
+            .find(|key| key.strip_prefix("sha256//").is_none_or(str::is_empty))
+        {
+            return Err(ProxyTesterConfigError::InvalidPinnedPublicKeyError(
+                key.clone(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Set up the TLS connections of the request
+    ///
+    pub(crate) fn apply(&self, request: &mut Request) -> Result<(), curl::Error> {
+        if let Some(path) = &self.ca_bundle {
+            request.cainfo(path)?;
+        }
+        if let Some(path) = &self.proxy_ca_bundle {
+            request.proxy_cainfo(&path.to_string_lossy())?;
+        }
+        if !self.verify_target {
+            request.ssl_verify_peer(false)?;
+            request.ssl_verify_host(false)?;
+        }
+        if !self.verify_proxy {
+            request.proxy_ssl_verify_peer(false)?;
+            request.proxy_ssl_verify_host(false)?;
+        }
+        if let Some(path) = &self.client_cert {
+            request.ssl_cert(path)?;
+        }
+        if let Some(path) = &self.client_key {
+            request.ssl_key(path)?;
+        }
+        if let Some(version) = self.min_version {
+            request.ssl_min_max_version(version.into(), SslVersion::Default)?;
+        }
+        if !self.pinned_public_keys.is_empty() {
+            request.pinned_public_key(&self.pinned_public_keys.join(";"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TlsOptions {
+    fn default() -> Self {
+        TlsOptions::new()
+    }
+}
+
+impl TlsSession {
+    ///
+    /// Parse the line curl logs once a TLS connection is up, like
+    /// `SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384 / X25519 / RSASSA-PSS`
+    ///
+    pub(crate) fn parse(line: &str) -> Option<TlsSession> {
+        let mut parts = line
+            .trim()
+            .strip_prefix("SSL connection using ")?
+            .split(" / ");
+        let version = parts.next()?.trim();
+        let cipher = parts.next()?.trim();
+        if version.is_empty() || cipher.is_empty() {
+            return None;
+        }
+
+        Some(TlsSession {
+            version: version.to_owned(),
+            cipher: cipher.to_owned(),
+        })
+    }
+}
+
+impl TlsVersion {
+    ///
+    /// Get the stable name of the version
+    ///
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TlsVersion::Tls10 => "1.0",
+            TlsVersion::Tls11 => "1.1",
+            TlsVersion::Tls12 => "1.2",
+            TlsVersion::Tls13 => "1.3",
+        }
+    }
+}
+
+impl Display for TlsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<TlsVersion> for SslVersion {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls10 => SslVersion::Tlsv10,
+            TlsVersion::Tls11 => SslVersion::Tlsv11,
+            TlsVersion::Tls12 => SslVersion::Tlsv12,
+            TlsVersion::Tls13 => SslVersion::Tlsv13,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tls_session() {
+        assert_eq!(
+            TlsSession::parse("SSL connection using TLSv1.3 / TLS_AES_256_GCM_SHA384\n"),
+            Some(TlsSession {
+                version: "TLSv1.3".to_owned(),
+                cipher: "TLS_AES_256_GCM_SHA384".to_owned(),
+            })
+        );
+        assert_eq!(
+            TlsSession::parse(
+                "SSL connection using TLSv1.2 / ECDHE-RSA-AES128-GCM-SHA256 / X25519 / RSASSA-PSS"
+            )
+            .map(|session| session.cipher),
+            Some("ECDHE-RSA-AES128-GCM-SHA256".to_owned())
+        );
+        assert_eq!(TlsSession::parse("Connected to host"), None);
+        assert_eq!(TlsSession::parse("SSL connection using TLSv1.3"), None);
+    }
+
+    #[test]
+    fn pinned_keys_must_be_hashes() {
+        assert!(TlsOptions::new()
+            .add_pinned_public_key("sha256//YhKJKSzoTt2b5FP18fvpHo7fJYqQCjAa3HWY3tvRMwE=")
+            .check()
+            .is_ok());
+
+        for key in ["sha256//", "YhKJKSzoTt2b5FP18fvpHo7fJYqQCjAa3HWY3tvRMwE="] {
+            assert!(matches!(
+                TlsOptions::new().add_pinned_public_key(key).check(),
+                Err(ProxyTesterConfigError::InvalidPinnedPublicKeyError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn files_must_exist() {
+        assert!(matches!(
+            TlsOptions::new()
+                .set_ca_bundle("does/not/exist.pem")
+                .check(),
+            Err(ProxyTesterConfigError::TlsFileError { .. })
+        ));
+        assert!(matches!(
+            TlsOptions::new()
+                .set_client_key("testdata/test_proxies.txt")
+                .check(),
+            Err(ProxyTesterConfigError::ClientKeyWithoutCertError)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn tls_version_serde_name() {
+        assert_eq!(serde_json::to_value(TlsVersion::Tls12).unwrap(), "1.2");
+    }
+}
@@ -0,0 +1,452 @@
+use std::ops::RangeInclusive;
+
+use regex::Regex;
+use thiserror::Error;
+
+///
+/// Rules a response has to satisfy for the proxy to count as working
+///
+/// Without any rules, every response below 400 is accepted. Proxies often answer
+/// with a captive portal or a block page instead of the target, which these rules catch.
+///
+/// # Examples
+///
+/// ```rust
+/// use proxytester::{ProxyTesterOptions, ResponseValidation};
+///
+/// let validation = ResponseValidation::new()
+///     .add_accepted_status(200..=299)
+///     .add_required_body_text("Example Domain")
+///     .add_forbidden_header("x-captive-portal")
+///     .set_max_body_size(64 * 1024);
+///
+/// let proxy_tester = ProxyTesterOptions::default()
+///     .set_validation(validation)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResponseValidation {
+    accepted_statuses: Vec<RangeInclusive<u32>>,
+    required_body_texts: Vec<String>,
+    required_body_patterns: Vec<String>,
+    required_headers: Vec<(String, Option<String>)>,
+    forbidden_headers: Vec<String>,
+    min_body_size: Option<usize>,
+    max_body_size: Option<usize>,
+}
+
+///
+/// Why a response failed validation
+///
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    #[error("status {0} is not accepted")]
+    UnacceptedStatusError(u32),
+
+    #[error("body does not contain {0:?}")]
+    MissingBodyTextError(String),
+
+    #[error("body does not match /{0}/")]
+    BodyMismatchError(String),
+
+    #[error("header {0} is missing")]
+    MissingHeaderError(String),
+
+    #[error("header {name} is {actual:?}, expected {expected:?}")]
+    HeaderMismatchError {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("header {0} is forbidden")]
+    ForbiddenHeaderError(String),
+
+    #[error("body is {size} bytes, expected at least {min}")]
+    BodyTooSmallError { size: usize, min: usize },
+
+    #[error("body is {size} bytes, expected at most {max}")]
+    BodyTooLargeError { size: usize, max: usize },
+}
+
+/// How much of the body is kept for the text and pattern rules
+pub const MAX_INSPECTED_BODY_SIZE: usize = 1024 * 1024;
+
+///
+/// The response of a request, as far as the rules are concerned
+///
+pub(crate) struct Response<'a> {
+    pub(crate) status: u32,
+    /// The headers of the final response, without the ones of a CONNECT
+    pub(crate) headers: &'a [(String, String)],
+    /// The start of the body, at most [MAX_INSPECTED_BODY_SIZE] bytes
+    pub(crate) body: &'a [u8],
+    pub(crate) body_size: usize,
+}
+
+///
+/// Response rules ready to be checked, with their patterns compiled
+///
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Validator {
+    rules: ResponseValidation,
+    patterns: Vec<Regex>,
+}
+
+impl ResponseValidation {
+    ///
+    /// Create rules which accept every response below 400
+    ///
+    pub fn new() -> ResponseValidation {
+        ResponseValidation::default()
+    }
+
+    ///
+    /// Accept the statuses in the range
+    ///
+    /// Once any range is added, statuses outside of all ranges fail validation,
+    /// instead of only 400 and above failing.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_accepted_status(mut self, statuses: RangeInclusive<u32>) -> Self {
+        self.accepted_statuses.push(statuses);
+        self
+    }
+
+    ///
+    /// Require the body to contain the text
+    ///
+    /// Only the first [MAX_INSPECTED_BODY_SIZE] bytes of the body are searched.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_required_body_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.required_body_texts.push(text.into());
+        self
+    }
+
+    ///
+    /// Require the body to match the regular expression
+    ///
+    /// The pattern is compiled when the ProxyTester is built. Only the first
+    /// [MAX_INSPECTED_BODY_SIZE] bytes of the body are searched.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_required_body_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.required_body_patterns.push(pattern.into());
+        self
+    }
+
+    ///
+    /// Require the response to have the header, optionally with exactly the value
+    ///
+    /// Header names are compared case-insensitively.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_required_header<S: Into<String>>(mut self, name: S, value: Option<S>) -> Self {
+        self.required_headers
+            .push((name.into(), value.map(Into::into)));
+        self
+    }
+
+    ///
+    /// Fail responses which have the header
+    ///
+    /// Header names are compared case-insensitively.
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn add_forbidden_header<S: Into<String>>(mut self, name: S) -> Self {
+        self.forbidden_headers.push(name.into());
+        self
+    }
+
+    ///
+    /// Set the smallest body size in bytes which is accepted
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_min_body_size(mut self, size: usize) -> Self {
+        self.min_body_size = Option::from(size);
+        self
+    }
+
+    ///
+    /// Set the largest body size in bytes which is accepted
+    ///
+    /// This is a fluent setter method which must be chained or used as it consumes self.
+    ///
+    pub fn set_max_body_size(mut self, size: usize) -> Self {
+        self.max_body_size = Option::from(size);
+        self
+    }
+
+    ///
+    /// Get the body size bounds, if any are set
+    ///
+    pub(crate) fn body_size_bounds(&self) -> (Option<usize>, Option<usize>) {
+        (self.min_body_size, self.max_body_size)
+    }
+
+    ///
+    /// Compile the patterns of the rules
+    ///
+    pub(crate) fn compile(self) -> Result<Validator, regex::Error> {
+        let patterns = self
+            .required_body_patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Validator {
+            rules: self,
+            patterns,
+        })
+    }
+}
+
+impl Validator {
+    ///
+    /// Check if the accepted statuses were chosen, instead of accepting everything below 400
+    ///
+    pub(crate) fn has_accepted_statuses(&self) -> bool {
+        !self.rules.accepted_statuses.is_empty()
+    }
+
+    ///
+    /// Check if the body has to be kept for the rules
+    ///
+    pub(crate) fn needs_body(&self) -> bool {
+        !self.rules.required_body_texts.is_empty() || !self.patterns.is_empty()
+    }
+
+    ///
+    /// Check the response against every rule, returning the first which fails
+    ///
+    pub(crate) fn validate(&self, response: &Response) -> Result<(), ValidationError> {
+        let rules = &self.rules;
+
+        if !rules.accepted_statuses.is_empty()
+            && !rules
+                .accepted_statuses
+                .iter()
+                .any(|statuses| statuses.contains(&response.status))
+        {
+            return Err(ValidationError::UnacceptedStatusError(response.status));
+        }
+
+        for (name, expected) in &rules.required_headers {
+            let actual = header(response.headers, name)
+                .ok_or_else(|| ValidationError::MissingHeaderError(name.clone()))?;
+            if let Some(expected) = expected {
+                if actual != expected {
+                    return Err(ValidationError::HeaderMismatchError {
+                        name: name.clone(),
+                        expected: expected.clone(),
+                        actual: actual.to_owned(),
+                    });
+                }
+            }
+        }
+
+        if let Some(name) = rules
+            .forbidden_headers
+            .iter()
+            .find(|name| header(response.headers, name).is_some())
+        {
+            return Err(ValidationError::ForbiddenHeaderError(name.clone()));
+        }
+
+        if let Some(min) = rules.min_body_size {
+            if response.body_size < min {
+                return Err(ValidationError::BodyTooSmallError {
+                    size: response.body_size,
+                    min,
+                });
+            }
+        }
+
+        if let Some(max) = rules.max_body_size {
+            if response.body_size > max {
+                return Err(ValidationError::BodyTooLargeError {
+                    size: response.body_size,
+                    max,
+                });
+            }
+        }
+
+        if self.needs_body() {
+            let body = String::from_utf8_lossy(response.body);
+
+            if let Some(text) = rules
+                .required_body_texts
+                .iter()
+                .find(|text| !body.contains(text.as_str()))
+            {
+                return Err(ValidationError::MissingBodyTextError(text.clone()));
+            }
+
+            if let Some(pattern) = self
+                .patterns
+                .iter()
+                .find(|pattern| !pattern.is_match(&body))
+            {
+                return Err(ValidationError::BodyMismatchError(
+                    pattern.as_str().to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// Find the value of a header by its case-insensitive name
+///
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response<'a>(status: u32, headers: &'a [(String, String)], body: &'a [u8]) -> Response<'a> {
+        Response {
+            status,
+            headers,
+            body,
+            body_size: body.len(),
+        }
+    }
+
+    fn headers(headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_rules_accept_anything() {
+        let validator = ResponseValidation::new().compile().unwrap();
+
+        assert!(!validator.needs_body());
+        assert_eq!(validator.validate(&response(302, &[], b"")), Ok(()));
+    }
+
+    #[test]
+    fn accepted_statuses() {
+        let validator = ResponseValidation::new()
+            .add_accepted_status(200..=204)
+            .add_accepted_status(301..=301)
+            .compile()
+            .unwrap();
+
+        assert_eq!(validator.validate(&response(204, &[], b"")), Ok(()));
+        assert_eq!(validator.validate(&response(301, &[], b"")), Ok(()));
+        assert_eq!(
+            validator.validate(&response(302, &[], b"")),
+            Err(ValidationError::UnacceptedStatusError(302))
+        );
+    }
+
+    #[test]
+    fn required_body_text_and_pattern() {
+        let validator = ResponseValidation::new()
+            .add_required_body_text("Welcome")
+            .add_required_body_pattern(r"order #\d+")
+            .compile()
+            .unwrap();
+
+        assert!(validator.needs_body());
+        assert_eq!(
+            validator.validate(&response(200, &[], b"Welcome, order #12")),
+            Ok(())
+        );
+        assert_eq!(
+            validator.validate(&response(200, &[], b"Login to the hotspot")),
+            Err(ValidationError::MissingBodyTextError("Welcome".to_owned()))
+        );
+        assert_eq!(
+            validator.validate(&response(200, &[], b"Welcome, order #")),
+            Err(ValidationError::BodyMismatchError(r"order #\d+".to_owned()))
+        );
+    }
+
+    #[test]
+    fn invalid_body_pattern() {
+        assert!(ResponseValidation::new()
+            .add_required_body_pattern("(")
+            .compile()
+            .is_err());
+    }
+
+    #[test]
+    fn required_and_forbidden_headers() {
+        let validator = ResponseValidation::new()
+            .add_required_header("Content-Type", Some("text/html"))
+            .add_required_header("server", None)
+            .add_forbidden_header("X-Blocked")
+            .compile()
+            .unwrap();
+
+        let good = headers(&[("content-type", "text/html"), ("Server", "nginx")]);
+        assert_eq!(validator.validate(&response(200, &good, b"")), Ok(()));
+
+        let missing = headers(&[("content-type", "text/html")]);
+        assert_eq!(
+            validator.validate(&response(200, &missing, b"")),
+            Err(ValidationError::MissingHeaderError("server".to_owned()))
+        );
+
+        let mismatch = headers(&[("content-type", "text/plain"), ("Server", "nginx")]);
+        assert_eq!(
+            validator.validate(&response(200, &mismatch, b"")),
+            Err(ValidationError::HeaderMismatchError {
+                name: "Content-Type".to_owned(),
+                expected: "text/html".to_owned(),
+                actual: "text/plain".to_owned(),
+            })
+        );
+
+        let blocked = headers(&[
+            ("content-type", "text/html"),
+            ("Server", "nginx"),
+            ("x-blocked", "1"),
+        ]);
+        assert_eq!(
+            validator.validate(&response(200, &blocked, b"")),
+            Err(ValidationError::ForbiddenHeaderError(
+                "X-Blocked".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn body_size_bounds() {
+        let validator = ResponseValidation::new()
+            .set_min_body_size(2)
+            .set_max_body_size(4)
+            .compile()
+            .unwrap();
+
+        assert_eq!(validator.validate(&response(200, &[], b"abc")), Ok(()));
+        assert_eq!(
+            validator.validate(&response(200, &[], b"a")),
+            Err(ValidationError::BodyTooSmallError { size: 1, min: 2 })
+        );
+        assert_eq!(
+            validator.validate(&response(200, &[], b"abcde")),
+            Err(ValidationError::BodyTooLargeError { size: 5, max: 4 })
+        );
+    }
+}